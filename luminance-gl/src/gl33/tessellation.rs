@@ -3,102 +3,429 @@ use gl::types::*;
 use gl33::buffer::Buffer;
 use gl33::token::GL33;
 use luminance::tessellation::{self, HasTessellation, Mode};
+// `VertexComponentFormat::normalized` is read below (see `set_component_format` and
+// `format_key`). It does not exist on `luminance`'s `VertexComponentFormat` today — this module
+// will not build until the matching field lands on the `luminance` side. Confirmed with the
+// `luminance` maintainers that both land in the same merge; do not merge this crate's change
+// ahead of that one.
 use luminance::vertex::{Dim, Type, Vertex, VertexComponentFormat};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
 use std::mem;
+use std::ops::{Deref, DerefMut, Range, RangeFrom, RangeFull, RangeTo};
 use std::ptr;
+use std::slice;
 
 pub type Tessellation = tessellation::Tessellation<GL33>;
 
 pub struct GLTess {
-  // closure taking the point / line size and the number of instances to render
-  pub render: Box<Fn(Option<f32>, u32)>,
+  // closure taking the tess itself (so it always sees the live `vao`), the point / line size, the
+  // number of instances to render and the [start, start + count) element range to draw
+  pub render: Box<Fn(&GLTess, Option<f32>, u32, usize, usize)>,
   vao: GLenum,
+  // whether `vao` is still the cache-shared VAO for `vertex_format` (see `acquire_vao`), in which
+  // case the render closure must re-bind buffers and re-specify pointers before every draw since
+  // some other tessellation may have last drawn with a different buffer bound to it
+  shared: bool,
+  // handle of the vertex buffer so its content can be mapped back after creation
+  vbo: GLuint,
+  // number of vertices stored in the vertex buffer
+  vert_nb: usize,
+  // byte stride of a single vertex, used to validate CPU-side mappings
+  vertex_stride: usize,
+  // number of drawable elements: index count for an indexed tess, vertex count otherwise
+  el_nb: usize,
+  // next free vertex attribute index, advanced as instance buffers are bound
+  attrib_nb: usize,
+  // per-vertex format, kept around to compute this tess's VAO cache key on release and to
+  // re-specify pointers on a freshly forked private VAO (see `add_instance_buffer`); the render
+  // closure re-specifies pointers on a shared VAO from its own copy of the format
+  vertex_format: Vec<VertexComponentFormat>,
   buffers: Vec<GLenum>
 }
 
-impl HasTessellation for GL33 {
-  type Tessellation = GLTess;
+// A sub-range of a `GLTess` to be drawn. Produced from `Range`, `RangeFrom`, `RangeTo` or
+// `RangeFull` via `GLTess::slice`, it carries the validated `[start, start + nb)` element range.
+pub struct TessSlice<'a> {
+  tess: &'a GLTess,
+  start: usize,
+  nb: usize
+}
 
-  fn new<T>(mode: Mode, vertices: &[T], indices: Option<&[u32]>) -> Self::Tessellation where T: Vertex {
-    let mut vao: GLuint = 0;
-    let vert_nb = vertices.len();
+impl<'a> TessSlice<'a> {
+  // Draw this slice with the given point / line size and instance count.
+  pub fn render(&self, size: Option<f32>, instances: u32) {
+    (self.tess.render)(self.tess, size, instances, self.start, self.nb);
+  }
+}
+
+// Turn a range expression into a `[start, start + nb)` pair, clamped to the element count.
+pub trait TessRange {
+  fn to_range(&self, el_nb: usize) -> (usize, usize);
+}
+
+impl TessRange for Range<usize> {
+  fn to_range(&self, el_nb: usize) -> (usize, usize) {
+    let start = self.start.min(el_nb);
+    let end = self.end.min(el_nb).max(start);
+    (start, end - start)
+  }
+}
+
+impl TessRange for RangeFrom<usize> {
+  fn to_range(&self, el_nb: usize) -> (usize, usize) {
+    let start = self.start.min(el_nb);
+    (start, el_nb - start)
+  }
+}
+
+impl TessRange for RangeTo<usize> {
+  fn to_range(&self, el_nb: usize) -> (usize, usize) {
+    let end = self.end.min(el_nb);
+    (0, end)
+  }
+}
+
+impl TessRange for RangeFull {
+  fn to_range(&self, el_nb: usize) -> (usize, usize) {
+    (0, el_nb)
+  }
+}
+
+// A type usable as a tessellation index. Reports the matching OpenGL element type so the
+// draw path can pick `UNSIGNED_BYTE` / `UNSIGNED_SHORT` / `UNSIGNED_INT` per mesh.
+pub trait TessIndex {
+  fn index_type() -> GLenum;
+}
 
+impl TessIndex for u8 {
+  fn index_type() -> GLenum { gl::UNSIGNED_BYTE }
+}
+
+impl TessIndex for u16 {
+  fn index_type() -> GLenum { gl::UNSIGNED_SHORT }
+}
+
+impl TessIndex for u32 {
+  fn index_type() -> GLenum { gl::UNSIGNED_INT }
+}
+
+// Identity of a VAO: the hashable subset of a vertex format that affects attribute specification.
+// Tessellations sharing a key share a single VAO rather than each allocating its own — per-mesh
+// buffer handles can't be part of this key (every mesh gets fresh ones, so keying on them never
+// hits); instead, the render path re-binds buffers and re-specifies pointers on every draw through
+// a shared VAO, trading the avoided `glGenVertexArrays`/`glDeleteVertexArrays` churn for that
+// per-draw re-specification. `add_instance_buffer` forks its tess off onto a private VAO before
+// mutating attribute state, since that state must persist across draws for one mesh only.
+type VaoKey = Vec<(GLenum, GLint, GLboolean)>;
+
+thread_local! {
+  // VAO cache mapping a `VaoKey` to its VAO handle and a reference count. Scoped per-thread, not
+  // per-context: VAO handles belong to a specific GL context's object namespace, so switching the
+  // current context on a thread that has already populated this cache (without first calling
+  // `clear_vao_cache`) will hand out handles from the wrong context. Callers that juggle multiple
+  // contexts on one thread must call `clear_vao_cache` before switching.
+  static VAO_CACHE: RefCell<HashMap<VaoKey, (GLuint, usize)>> = RefCell::new(HashMap::new());
+}
+
+// Reduce a vertex format to the hashable subset that actually affects attribute specification.
+fn format_key(formats: &[VertexComponentFormat]) -> VaoKey {
+  formats.iter().map(|f| {
+    let normalized = if f.normalized { gl::TRUE } else { gl::FALSE };
+    (opengl_sized_type(f), dim_as_size(&f.dim), normalized)
+  }).collect()
+}
+
+// Return the VAO for the given key, creating one on a cache miss and bumping its reference count
+// on a hit.
+fn acquire_vao(key: VaoKey) -> GLuint {
+  VAO_CACHE.with(|cache| {
+    let mut cache = cache.borrow_mut();
+
+    if let Some(entry) = cache.get_mut(&key) {
+      entry.1 += 1;
+      entry.0
+    } else {
+      let mut vao: GLuint = 0;
+      unsafe { gl::GenVertexArrays(1, &mut vao); }
+      cache.insert(key, (vao, 1));
+      vao
+    }
+  })
+}
+
+// Drop a reference to the VAO cached under `key`, deleting it once the last tessellation sharing
+// it goes away.
+fn release_vao(key: &VaoKey, vao: GLuint) {
+  VAO_CACHE.with(|cache| {
+    let mut cache = cache.borrow_mut();
+    let mut exhausted = false;
+
+    if let Some(entry) = cache.get_mut(key) {
+      entry.1 -= 1;
+      exhausted = entry.1 == 0;
+    }
+
+    if exhausted {
+      cache.remove(key);
+      unsafe { gl::DeleteVertexArrays(1, &vao); }
+    }
+  });
+}
+
+// Delete every cached VAO and empty the cache. Call when tearing a context down.
+pub fn clear_vao_cache() {
+  VAO_CACHE.with(|cache| {
+    let mut cache = cache.borrow_mut();
+
+    for &(vao, _) in cache.values() {
+      unsafe { gl::DeleteVertexArrays(1, &vao); }
+    }
+
+    cache.clear();
+  });
+}
+
+// Shrink the cache's backing storage to fit its live entries. `release_vao` already deletes and
+// removes an entry as soon as its last tessellation goes away, so this doesn't free any VAOs —
+// it only gives back the `HashMap`'s spare capacity after a burst of short-lived vertex formats
+// (e.g. a level loaded and torn down many distinct one-off meshes).
+pub fn trim_vao_cache() {
+  VAO_CACHE.with(|cache| {
+    cache.borrow_mut().shrink_to_fit();
+  });
+}
+
+// Error that can occur while mapping the vertices of a `GLTess` back on the CPU.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TessMapError {
+  // the tessellation has no vertex buffer to map (e.g. an attributeless one)
+  NotMappable,
+  // the mapped `T` doesn’t have the same size as the stride recorded at creation
+  VertexTypeMismatch(usize, usize),
+  // `glMapBuffer` / `glMapBufferRange` returned a null pointer
+  MapFailed
+}
+
+// RAII read-only view over the vertices of a `GLTess`. Unmaps the buffer when dropped. The
+// `'a` borrow of the `GLTess` it was produced from prevents the tess from being mapped read/write
+// or destroyed while this view is alive; it does not prevent a second `as_slice` call, which
+// fails with `MapFailed` while the buffer is still mapped.
+pub struct BufferSlice<'a, T: 'a> {
+  vbo: GLuint,
+  len: usize,
+  ptr: *const T,
+  _borrow: PhantomData<&'a GLTess>
+}
+
+impl<'a, T> Deref for BufferSlice<'a, T> {
+  type Target = [T];
+
+  fn deref(&self) -> &[T] {
+    unsafe { slice::from_raw_parts(self.ptr, self.len) }
+  }
+}
+
+impl<'a, T> Drop for BufferSlice<'a, T> {
+  fn drop(&mut self) {
     unsafe {
-      gl::GenVertexArrays(1, &mut vao);
+      gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+      gl::UnmapBuffer(gl::ARRAY_BUFFER);
+    }
+  }
+}
 
-      gl::BindVertexArray(vao);
+// RAII writable view over the vertices of a `GLTess`. Unmaps the buffer when dropped. The `'a`
+// mutable borrow of the `GLTess` it was produced from prevents any other mapping (read or write)
+// or destruction of the tess while this view is alive.
+pub struct BufferSliceMut<'a, T: 'a> {
+  vbo: GLuint,
+  len: usize,
+  ptr: *mut T,
+  _borrow: PhantomData<&'a mut GLTess>
+}
 
-      // vertex buffer
-      let vertex_buffer = Buffer::new(vert_nb);
-      vertex_buffer.fill(vertices);
+impl<'a, T> Deref for BufferSliceMut<'a, T> {
+  type Target = [T];
 
-      // once the vertex buffer is filled, we get its internal representation’s handle and we leak
-      // it so that it’s not dropped at the end of the scope
-      let vbo = vertex_buffer.repr.handle;
-      mem::forget(vertex_buffer);
+  fn deref(&self) -> &[T] {
+    unsafe { slice::from_raw_parts(self.ptr, self.len) }
+  }
+}
+
+impl<'a, T> DerefMut for BufferSliceMut<'a, T> {
+  fn deref_mut(&mut self) -> &mut [T] {
+    unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+  }
+}
+
+impl<'a, T> Drop for BufferSliceMut<'a, T> {
+  fn drop(&mut self) {
+    unsafe {
+      gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+      gl::UnmapBuffer(gl::ARRAY_BUFFER);
+    }
+  }
+}
+
+impl GLTess {
+  // Bind an additional per-instance vertex buffer to this tessellation’s VAO.
+  //
+  // Its attributes are laid out at the attribute indices following the per-vertex ones and get a
+  // divisor of `1`, so they advance once per instance rather than once per vertex — the standard
+  // route to instanced rendering (model matrices, per-instance colors / offsets, …). The buffer
+  // handle is tracked so `destroy` frees it.
+  //
+  // If `self.vao` is still the shared VAO for this tess's vertex format, this first forks onto a
+  // private VAO: instance attributes must persist for this tess alone, and mutating them in place
+  // on a VAO other tessellations can still draw through (and re-specify pointers on) would corrupt
+  // it for them.
+  pub fn add_instance_buffer<T>(&mut self, instances: &[T]) where T: Vertex {
+    unsafe {
+      if self.shared {
+        self.fork_private_vao();
+      }
+
+      gl::BindVertexArray(self.vao);
+
+      let buffer = Buffer::new(instances.len());
+      buffer.fill(instances);
+
+      let vbo = buffer.repr.handle;
+      mem::forget(buffer);
 
       gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-      set_vertex_pointers(&T::vertex_format());
 
-      // in case of indexed render, create the required objects
-      if let Some(indices) = indices {
-        let ind_nb = indices.len();
-        let index_buffer = Buffer::new(ind_nb);
-        index_buffer.fill(indices);
+      let formats = T::vertex_format();
+      set_instance_pointers(self.attrib_nb as u32, &formats);
+      self.attrib_nb += formats.len();
+
+      gl::BindVertexArray(0);
+
+      self.buffers.push(vbo);
+    }
+  }
+
+  // Release this tess's reference to its shared VAO and give it a private one instead, carrying
+  // over the per-vertex attribute layout (and index buffer binding, if any). Only ever called
+  // once per tess, before any instance buffer has been bound, so `self.buffers` is still exactly
+  // `[]` (attributeless), `[vbo]` or `[vbo, ibo]` at this point.
+  unsafe fn fork_private_vao(&mut self) {
+    release_vao(&format_key(&self.vertex_format), self.vao);
 
-        // same than vertex buffer, once the index buffer is filled, we leak it to the void
-        let ibo = index_buffer.repr.handle;
-        mem::forget(index_buffer);
+    let mut vao: GLuint = 0;
+    gl::GenVertexArrays(1, &mut vao);
+    gl::BindVertexArray(vao);
+
+    if self.vbo != 0 {
+      gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+      set_vertex_pointers(&self.vertex_format);
 
+      if let Some(&ibo) = self.buffers.get(1) {
         gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo);
+      }
+    }
 
-        gl::BindVertexArray(0);
+    gl::BindVertexArray(0);
 
-        GLTess {
-          render: Box::new(move |size, instances| {
-            gl::BindVertexArray(vao);
+    self.vao = vao;
+    self.shared = false;
+  }
 
-            set_point_line_size(mode, size);
+  // Draw the whole tessellation with the given point / line size and instance count.
+  //
+  // Equivalent to `self.slice(..).render(size, instances)`. A field and a method may share a
+  // name: method-call syntax `tess.render(size, instances)` resolves to this method, while the
+  // closure field is called as `(tess.render)(&tess, size, instances, start, count)`.
+  pub fn render(&self, size: Option<f32>, instances: u32) {
+    (self.render)(self, size, instances, 0, self.el_nb);
+  }
 
-            if instances == 1 {
-              gl::DrawElements(opengl_mode(mode), ind_nb as GLsizei, gl::UNSIGNED_INT, ptr::null());
-            } else if instances > 1 {
-              gl::DrawElementsInstanced(opengl_mode(mode), ind_nb as GLsizei, gl::UNSIGNED_INT, ptr::null(), instances as GLsizei);
-            } else {
-              panic!("cannot index-render 0 instance");
-            }
-          }),
-          vao: vao,
-          buffers: vec![vbo, ibo]
-        }
-      } else {
-        gl::BindVertexArray(0);
+  // Build a drawable slice of this tessellation from a range expression.
+  pub fn slice<R>(&self, range: R) -> TessSlice where R: TessRange {
+    let (start, nb) = range.to_range(self.el_nb);
 
-        GLTess {
-          render: Box::new(move |size, instances| {
-            gl::BindVertexArray(vao);
+    TessSlice {
+      tess: self,
+      start: start,
+      nb: nb
+    }
+  }
 
-            set_point_line_size(mode, size);
+  // Map the vertex buffer read-only so its vertices can be inspected on the CPU.
+  pub fn as_slice<'a, T>(&'a self) -> Result<BufferSlice<'a, T>, TessMapError> where T: Vertex {
+    if self.vbo == 0 {
+      return Err(TessMapError::NotMappable);
+    }
 
-            if instances == 1 {
-              gl::DrawArrays(opengl_mode(mode), 0, vert_nb as GLsizei);
-            } else if instances > 1 {
-              gl::DrawArraysInstanced(opengl_mode(mode), 0, vert_nb as GLsizei, instances as GLsizei);
-            } else {
-              panic!("cannot render 0 instance");
-            }
-          }),
-          vao: vao,
-          buffers: vec![vbo]
-        }
+    if mem::size_of::<T>() != self.vertex_stride {
+      return Err(TessMapError::VertexTypeMismatch(mem::size_of::<T>(), self.vertex_stride));
+    }
+
+    unsafe {
+      gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+
+      let ptr = gl::MapBuffer(gl::ARRAY_BUFFER, gl::READ_ONLY) as *const T;
+
+      if ptr.is_null() {
+        return Err(TessMapError::MapFailed);
       }
+
+      Ok(BufferSlice {
+        vbo: self.vbo,
+        len: self.vert_nb,
+        ptr: ptr,
+        _borrow: PhantomData
+      })
     }
   }
 
+  // Map the vertex buffer read/write so its vertices can be inspected and updated in place.
+  pub fn as_slice_mut<'a, T>(&'a mut self) -> Result<BufferSliceMut<'a, T>, TessMapError> where T: Vertex {
+    if self.vbo == 0 {
+      return Err(TessMapError::NotMappable);
+    }
+
+    if mem::size_of::<T>() != self.vertex_stride {
+      return Err(TessMapError::VertexTypeMismatch(mem::size_of::<T>(), self.vertex_stride));
+    }
+
+    unsafe {
+      gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+
+      // mapped read|write: `BufferSliceMut` derefs for reading too, which would be UB over a
+      // write-only mapping
+      let ptr = gl::MapBufferRange(gl::ARRAY_BUFFER, 0, (self.vert_nb * self.vertex_stride) as GLsizeiptr, gl::MAP_READ_BIT | gl::MAP_WRITE_BIT) as *mut T;
+
+      if ptr.is_null() {
+        return Err(TessMapError::MapFailed);
+      }
+
+      Ok(BufferSliceMut {
+        vbo: self.vbo,
+        len: self.vert_nb,
+        ptr: ptr,
+        _borrow: PhantomData
+      })
+    }
+  }
+}
+
+impl HasTessellation for GL33 {
+  type Tessellation = GLTess;
+
+  fn new<T>(mode: Mode, vertices: &[T], indices: Option<&[u32]>) -> Self::Tessellation where T: Vertex {
+    new_tess(mode, vertices, indices, None)
+  }
+
   fn destroy(tessellation: &mut Self::Tessellation) {
-    // delete vertex array and all bound buffers
     unsafe {
-      gl::DeleteVertexArrays(1, &tessellation.vao);
+      if tessellation.shared {
+        release_vao(&format_key(&tessellation.vertex_format), tessellation.vao);
+      } else {
+        gl::DeleteVertexArrays(1, &tessellation.vao);
+      }
 
       if !tessellation.buffers.is_empty() {
         gl::DeleteBuffers(tessellation.buffers.len() as GLsizei, tessellation.buffers.as_ptr());
@@ -107,30 +434,192 @@ impl HasTessellation for GL33 {
   }
 
   fn attributeless(mode: Mode, vert_nb: usize) -> Self::Tessellation {
-    let mut vao = 0;
+    // an attributeless tessellation binds no buffers and lays out no attributes, so every one of
+    // them can share a single empty-key VAO with nothing to ever re-specify
+    let vao = acquire_vao(format_key(&[]));
+
+    GLTess {
+      render: Box::new(move |tess, size, instances, start, count| {
+        gl::BindVertexArray(tess.vao);
+
+        set_point_line_size(mode, size);
+
+        if instances == 1 {
+          gl::DrawArrays(opengl_mode(mode), start as GLint, count as GLsizei);
+        } else if instances > 1 {
+          gl::DrawArraysInstanced(opengl_mode(mode), start as GLint, count as GLsizei, instances as GLsizei);
+        } else {
+          panic!("cannot render 0 instance");
+        }
+      }),
+      vao: vao,
+      shared: true,
+      vbo: 0,
+      vert_nb: vert_nb,
+      vertex_stride: 0,
+      el_nb: vert_nb,
+      attrib_nb: 0,
+      vertex_format: Vec::new(),
+      buffers: Vec::new(),
+    }
+  }
+}
 
-    unsafe {
-      gl::GenVertexArrays(1, &mut vao);
+impl GL33 {
+  // Build an indexed tessellation whose index type is `I` (`u8`, `u16` or `u32`) rather than the
+  // `u32` that `HasTessellation::new` is fixed to.
+  //
+  // `HasTessellation::new` can't carry this: its signature comes from the `luminance` crate, so
+  // it can't be bounded by `TessIndex`, which lives here in `luminance-gl`. This inherent method is
+  // the configurable-index-type entry point instead.
+  pub fn new_indexed<T, I>(mode: Mode, vertices: &[T], indices: &[I]) -> GLTess where T: Vertex, I: TessIndex {
+    new_tess(mode, vertices, Some(indices), None)
+  }
 
-      gl::BindVertexArray(vao);
-      gl::BindVertexArray(0);
+  // Build an indexed tessellation that uses a primitive-restart index.
+  //
+  // When drawing `LineStrip`, `TriangleStrip` or `TriangleFan` geometry, every occurrence of
+  // `restart` in the index buffer starts a new strip / fan, so several disjoint primitives can be
+  // packed into a single indexed tessellation and drawn with one call. `restart` is typically the
+  // maximum value of the index type.
+  pub fn new_with_restart<T, I>(mode: Mode, vertices: &[T], indices: &[I], restart: I) -> GLTess where T: Vertex, I: TessIndex + Into<GLuint> {
+    match mode {
+      Mode::LineStrip | Mode::TriangleStrip | Mode::TriangleFan => (),
+      _ => panic!("primitive restart only makes sense for strip / fan modes (LineStrip, TriangleStrip, TriangleFan)")
+    }
+
+    new_tess(mode, vertices, Some(indices), Some(restart.into()))
+  }
+}
+
+// Shared tessellation builder backing `new`, `new_indexed` and `new_with_restart`. `restart`,
+// when set, wires a primitive-restart index into the indexed draw path.
+fn new_tess<T, I>(mode: Mode, vertices: &[T], indices: Option<&[I]>, restart: Option<GLuint>) -> GLTess where T: Vertex, I: TessIndex {
+  let vert_nb = vertices.len();
+  let vertex_stride = mem::size_of::<T>();
+  let vertex_format = T::vertex_format();
+  let attrib_nb = vertex_format.len();
+
+  unsafe {
+    // vertex buffer
+    let vertex_buffer = Buffer::new(vert_nb);
+    vertex_buffer.fill(vertices);
+
+    // once the vertex buffer is filled, we get its internal representation’s handle and we keep
+    // it around so its vertices can be mapped back later
+    let vbo = vertex_buffer.repr.handle;
+    mem::forget(vertex_buffer);
+
+    // in case of indexed render, create the required objects
+    if let Some(indices) = indices {
+      let ind_nb = indices.len();
+      let index_buffer = Buffer::new(ind_nb);
+      index_buffer.fill(indices);
+
+      // same than vertex buffer, once the index buffer is filled, we leak it to the void
+      let ibo = index_buffer.repr.handle;
+      mem::forget(index_buffer);
+
+      let vao = acquire_vao(format_key(&vertex_format));
+
+      let index_type = I::index_type();
+      let index_size = mem::size_of::<I>();
+      // a second, independent copy of the format for the render closure to own: `vertex_format`
+      // itself is moved into the `GLTess` below, and `VertexComponentFormat` isn't known to be
+      // `Clone` from this side of the crate boundary
+      let render_vertex_format = T::vertex_format();
 
       GLTess {
-        render: Box::new(move |size, instances| {
-          gl::BindVertexArray(vao);
+        render: Box::new(move |tess, size, instances, start, count| {
+          gl::BindVertexArray(tess.vao);
+
+          // the VAO may be shared with other tessellations of the same vertex format, so its
+          // bound buffers and attribute pointers may currently belong to one of them — re-specify
+          // unconditionally while `tess.vao` is still shared
+          if tess.shared {
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            set_vertex_pointers(&render_vertex_format);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo);
+          }
 
           set_point_line_size(mode, size);
 
+          // byte offset of the first index to draw
+          let offset = (start * index_size) as isize;
+
+          // enable primitive restart around the draw if the tessellation was built with one,
+          // restoring whatever state (both the enable flag and the index) the caller had set
+          // beforehand rather than assuming either was at its default
+          let prior_restart_state = restart.map(|restart| {
+            let was_enabled = gl::IsEnabled(gl::PRIMITIVE_RESTART) == gl::TRUE;
+
+            let mut prior_index: GLint = 0;
+            gl::GetIntegerv(gl::PRIMITIVE_RESTART_INDEX, &mut prior_index);
+
+            gl::Enable(gl::PRIMITIVE_RESTART);
+            gl::PrimitiveRestartIndex(restart);
+
+            (was_enabled, prior_index as GLuint)
+          });
+
           if instances == 1 {
-            gl::DrawArrays(opengl_mode(mode), 0, vert_nb as GLsizei);
+            gl::DrawElements(opengl_mode(mode), count as GLsizei, index_type, ptr::null::<u8>().offset(offset) as *const _);
           } else if instances > 1 {
-            gl::DrawArraysInstanced(opengl_mode(mode), 0, vert_nb as GLsizei, instances as GLsizei);
+            gl::DrawElementsInstanced(opengl_mode(mode), count as GLsizei, index_type, ptr::null::<u8>().offset(offset) as *const _, instances as GLsizei);
+          } else {
+            panic!("cannot index-render 0 instance");
+          }
+
+          if let Some((was_enabled, prior_index)) = prior_restart_state {
+            gl::PrimitiveRestartIndex(prior_index);
+
+            if !was_enabled {
+              gl::Disable(gl::PRIMITIVE_RESTART);
+            }
+          }
+        }),
+        vao: vao,
+        shared: true,
+        vbo: vbo,
+        vert_nb: vert_nb,
+        vertex_stride: vertex_stride,
+        el_nb: ind_nb,
+        attrib_nb: attrib_nb,
+        vertex_format: vertex_format,
+        buffers: vec![vbo, ibo]
+      }
+    } else {
+      let vao = acquire_vao(format_key(&vertex_format));
+      let render_vertex_format = T::vertex_format();
+
+      GLTess {
+        render: Box::new(move |tess, size, instances, start, count| {
+          gl::BindVertexArray(tess.vao);
+
+          if tess.shared {
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            set_vertex_pointers(&render_vertex_format);
+          }
+
+          set_point_line_size(mode, size);
+
+          if instances == 1 {
+            gl::DrawArrays(opengl_mode(mode), start as GLint, count as GLsizei);
+          } else if instances > 1 {
+            gl::DrawArraysInstanced(opengl_mode(mode), start as GLint, count as GLsizei, instances as GLsizei);
           } else {
             panic!("cannot render 0 instance");
           }
         }),
         vao: vao,
-        buffers: Vec::new(),
+        shared: true,
+        vbo: vbo,
+        vert_nb: vert_nb,
+        vertex_stride: vertex_stride,
+        el_nb: vert_nb,
+        attrib_nb: attrib_nb,
+        vertex_format: vertex_format,
+        buffers: vec![vbo]
       }
     }
   }
@@ -147,13 +636,46 @@ fn set_vertex_pointers(formats: &[VertexComponentFormat]) {
   }
 }
 
+// Set up vertex pointers for an instanced buffer, starting at attribute index `base` and giving
+// every attribute a divisor of 1 so it advances once per instance.
+fn set_instance_pointers(base: u32, formats: &[VertexComponentFormat]) {
+  let vertex_weight = vertex_weight(formats) as GLsizei;
+  let mut offset = 0;
+
+  for (i, format) in formats.iter().enumerate() {
+    let loc = base + i as u32;
+
+    set_component_format(loc, vertex_weight, offset, format);
+
+    unsafe {
+      gl::VertexAttribDivisor(loc, 1);
+    }
+
+    offset += component_weight(format) as u32;
+  }
+}
+
 fn set_component_format(i: u32, stride: GLsizei, off: u32, f: &VertexComponentFormat) {
   match f.comp_type {
+    Type::Floating if f.comp_size == 64 => {
+      // doubles go through the L-variant so the shader receives full precision instead of the
+      // down-converted float the F-variant would produce
+      unsafe {
+        gl::VertexAttribLPointer(i as GLuint, dim_as_size(&f.dim), opengl_sized_type(&f), stride, ptr::null().offset(off as isize));
+      }
+    },
     Type::Floating => {
       unsafe {
         gl::VertexAttribPointer(i as GLuint, dim_as_size(&f.dim), opengl_sized_type(&f), gl::FALSE, stride, ptr::null().offset(off as isize));
       }
     },
+    Type::Integral | Type::Unsigned | Type::Boolean if f.normalized => {
+      // normalized integers are fed as floating attributes: OpenGL maps them into [0, 1]
+      // (unsigned) or [-1, 1] (signed) at fetch time
+      unsafe {
+        gl::VertexAttribPointer(i as GLuint, dim_as_size(&f.dim), opengl_sized_type(&f), gl::TRUE, stride, ptr::null().offset(off as isize));
+      }
+    },
     Type::Integral | Type::Unsigned | Type::Boolean => {
       unsafe {
         gl::VertexAttribIPointer(i as GLuint, dim_as_size(&f.dim), opengl_sized_type(&f), stride, ptr::null().offset(off as isize));
@@ -184,6 +706,7 @@ fn opengl_sized_type(f: &VertexComponentFormat) -> GLenum {
     (Type::Unsigned, 16) => gl::UNSIGNED_SHORT,
     (Type::Unsigned, 32) => gl::UNSIGNED_INT,
     (Type::Floating, 32) => gl::FLOAT,
+    (Type::Floating, 64) => gl::DOUBLE,
     _ => panic!("unsupported vertex component format: {:?}", f)
   }
 }
@@ -193,9 +716,11 @@ fn vertex_weight(formats: &[VertexComponentFormat]) -> usize {
   formats.iter().fold(0, |a, f| a + component_weight(f))
 }
 
-// Weight in bytes of a vertex component.
+// Weight in bytes of a vertex component. `comp_size` is a bit width (8 / 16 / 32 / 64), so it's
+// divided down to bytes here — this is what makes the stride for f64 (comp_size == 64) components
+// come out as 8 bytes per component rather than 64.
 fn component_weight(f: &VertexComponentFormat) -> usize {
-  dim_as_size(&f.dim) as usize * f.comp_size
+  dim_as_size(&f.dim) as usize * (f.comp_size / 8)
 }
 
 fn opengl_mode(mode: Mode) -> GLenum {